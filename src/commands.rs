@@ -0,0 +1,66 @@
+//! Command dispatcher for `@pisearch` mentions.
+//!
+//! The mention handler used to understand only a bare numeric search.
+//! `dispatch` adds a small routing table of verbs so a reply like
+//! "@pisearch position 31415" or "@pisearch count 14159" is answered by its
+//! own handler, while anything else keeps falling through to the original
+//! substring search via `extract_number`/`do_pisearch` so existing
+//! interactions don't change.
+
+use anyhow::anyhow;
+use regex::Regex;
+
+use crate::pi_index::PiBackend;
+use crate::do_pisearch;
+
+/// How many digits to show back for a `position` reverse lookup.
+const REVERSE_LOOKUP_LEN: usize = 15;
+
+fn position_re() -> Regex {
+    Regex::new(r"@pisearch\b.*?\bposition\s+(\d+)").unwrap()
+}
+
+fn stats_re() -> Regex {
+    Regex::new(r"@pisearch\b.*?\b(?:count|stats|occurrences?)\s+(\d[\d\-]*)").unwrap()
+}
+
+/// Routes the text of a mention to the right handler, falling back to the
+/// default numeric search when no verb matches.
+pub async fn dispatch(backend: &dyn PiBackend, text: &str) -> anyhow::Result<String> {
+    if let Some(caps) = position_re().captures(text) {
+        let position: u64 = caps[1].parse()?;
+        return handle_position(backend, position).await;
+    }
+    if let Some(caps) = stats_re().captures(text) {
+        let number = caps[1].replace('-', "");
+        return handle_stats(backend, &number).await;
+    }
+    do_pisearch(backend, text).await
+}
+
+async fn handle_position(backend: &dyn PiBackend, position: u64) -> anyhow::Result<String> {
+    if position == 0 {
+        return Err(anyhow!("position is 1-based; 0 isn't a valid digit offset"));
+    }
+    match backend.digits_at(position, REVERSE_LOOKUP_LEN).await? {
+        Some(digits) => Ok(format!(
+            "Starting at position {position} in pi: {digits}...\n\nFind all the #pi you can eat at https://angio.net/pi/"
+        )),
+        None => Ok(format!(
+            "Sorry, position {position} is out of range for what I've got indexed."
+        )),
+    }
+}
+
+async fn handle_stats(backend: &dyn PiBackend, number: &str) -> anyhow::Result<String> {
+    let digits = crate::pi_index::describe_digit_count(backend.digit_count());
+    match backend.search(number).await? {
+        Some(pi_match) => Ok(format!(
+            "{number} appears {} times in the first {digits} digits of pi, first at position {}.\n\nFind all the #pi you can eat at https://angio.net/pi/",
+            pi_match.count, pi_match.position
+        )),
+        None => Ok(format!(
+            "Sorry, I couldn't find {number} in the first {digits} digits of Pi."
+        )),
+    }
+}
@@ -0,0 +1,28 @@
+//! Resolves the account we watch for mentions, so operators can configure
+//! `watch_did` with either a raw DID or a handle (e.g.
+//! `pisearch.bsky.social`) instead of having to look up the DID by hand and
+//! rotate it whenever the bot's handle changes.
+
+use atrium_api::com::atproto::identity::resolve_handle;
+use atrium_api::types::string::{Did, Handle};
+use bsky_sdk::BskyAgent;
+
+/// Resolves `watch_did` to a DID. If it already looks like a DID it's used
+/// as-is; otherwise it's treated as a handle and resolved via
+/// `com.atproto.identity.resolveHandle`. Callers should resolve once at
+/// startup and reuse the result for the life of the process.
+pub async fn resolve_watch_did(agent: &BskyAgent, watch_did: &str) -> anyhow::Result<Did> {
+    if watch_did.starts_with("did:") {
+        return Ok(Did::new(watch_did.to_string())?);
+    }
+
+    let handle = Handle::new(watch_did.to_string())?;
+    let output = agent
+        .api
+        .com
+        .atproto
+        .identity
+        .resolve_handle(resolve_handle::ParametersData { handle }.into())
+        .await?;
+    Ok(output.did.clone())
+}
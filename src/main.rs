@@ -15,6 +15,16 @@ use jetstream_oxide::{
 use serde::Deserialize;
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
+
+mod commands;
+mod identity;
+mod pi_index;
+mod records;
+mod session;
+mod stream_state;
+
+use pi_index::{AngioBackend, LocalPiIndex, PiBackend};
 
 #[derive(Deserialize)]
 struct Credentials {
@@ -60,13 +70,6 @@ async fn search_pi_str(number: &str) -> Result<PiSearchResult, Box<dyn std::erro
     Ok(result)
 }
 
-async fn search_pi(number: u32) -> Result<PiSearchResult, Box<dyn std::error::Error>> {
-    let url = format!("https://www.angio.net/newpi/piquery?q={}", number);
-    let response = reqwest::get(&url).await?.text().await?;
-    let result: PiSearchResult = serde_json::from_str(&response)?;
-    Ok(result)
-}
-
 fn generate_random_number() -> u32 {
     fastrand::u32(0..100_000_000)
 }
@@ -90,15 +93,47 @@ struct Cli {
         help = "Dry run mode, just print the post content without actually posting"
     )]
     dry_run: bool,
+    #[arg(
+        long,
+        requires = "index",
+        help = "Path to a flat ASCII digits-of-pi file; serves searches locally instead of angio.net"
+    )]
+    pi_file: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "pi_file",
+        help = "Path to the suffix-array index built from --pi-file"
+    )]
+    index: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
 
+fn build_backend(cli: &Cli) -> Result<Box<dyn PiBackend>, Box<dyn std::error::Error>> {
+    match (&cli.pi_file, &cli.index) {
+        (Some(digits_path), Some(index_path)) => {
+            Ok(Box::new(LocalPiIndex::open(digits_path, index_path)?))
+        }
+        _ => Ok(Box::new(AngioBackend)),
+    }
+}
+
 #[derive(Subcommand, PartialEq)]
 enum Commands {
     Random,
     Today,
     Stream,
+    /// List the bot's recent posts (rkey and text).
+    List,
+    /// Delete one of the bot's posts by rkey.
+    Delete { rkey: String },
+    /// Build the suffix-array index file that `--pi-file`/`--index` needs.
+    BuildIndex {
+        /// Flat ASCII digits-of-pi file to index.
+        pi_file: PathBuf,
+        /// Path to write the suffix-array index to.
+        index: PathBuf,
+    },
 }
 
 async fn post_to_bsky(
@@ -125,54 +160,113 @@ async fn post_to_bsky(
             record_data
         );
     } else {
-        agent.create_record(record_data).await?;
+        session::with_refresh(agent, || async {
+            agent
+                .create_record(record_data.clone())
+                .await
+                .map_err(|e| anyhow::anyhow!("{e}"))
+        })
+        .await?;
     }
     Ok(())
 }
 
+fn post_uri(did: &str, collection: &str, rkey: &str) -> String {
+    format!("at://{}/{}/{}", did, collection, rkey)
+}
+
+/// Jetstream occasionally drops the websocket; rather than exit and stop
+/// replying, reconnect with an exponential backoff, capped at one minute.
+const MAX_RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(60);
+
 async fn streaming_mode(
     credentials: &Credentials,
+    backend: &dyn PiBackend,
     dry_run: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let agent = BskyAgent::builder().build().await?;
-    let _session = agent.login(&credentials.username, &credentials.password).await?;
+    let agent = session::login(&credentials.username, &credentials.password).await?;
     println!("Streaming mode!");
-    let target_did = atrium_api::types::string::Did::new((&credentials.watch_did).into())?;
+    let target_did = identity::resolve_watch_did(&agent, &credentials.watch_did).await?;
     let nsid = jetstream_oxide::exports::Nsid::new("app.bsky.feed.post".into()).unwrap();
-    let config = JetstreamConfig {
-        wanted_collections: vec![nsid],
-        endpoint: DefaultJetstreamEndpoints::USEastTwo.into(),
-        compression: JetstreamCompression::Zstd,
-        ..Default::default()
-    };
 
-    let jetstream = JetstreamConnector::new(config).unwrap();
-    let receiver = jetstream.connect().await?;
-
-    while let Ok(event) = receiver.recv_async().await {
-        if let Commit(CommitEvent::Create { info, commit, .. }) = event {
-            let event_did = info.did.to_string();
-            if let AppBskyFeedPost(record) = &commit.record {
-                let matches = record.facets.as_ref().is_some_and(|facets| {
-                    facets.iter().any(|facet| {
-                        facet.data.features.iter().any(|feature| {
-                            matches!(feature, Union::Refs(Mention(m)) if m.data.did == target_did)
+    let mut cursor = stream_state::load_cursor();
+    let mut seen = stream_state::load_seen();
+    let mut backoff = std::time::Duration::from_secs(1);
+    // The firehose delivers every app.bsky.feed.post create, not just
+    // mentions of us, so writing the cursor file on every event would be
+    // thousands of blocking disk writes per minute. Persist it on a time
+    // interval instead, plus immediately after we actually act on a
+    // mention so a crash right after replying doesn't lose that progress.
+    let mut last_cursor_save = std::time::Instant::now();
+    const CURSOR_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+    loop {
+        let config = JetstreamConfig {
+            wanted_collections: vec![nsid.clone()],
+            endpoint: DefaultJetstreamEndpoints::USEastTwo.into(),
+            compression: JetstreamCompression::Zstd,
+            cursor,
+            ..Default::default()
+        };
+
+        let jetstream = JetstreamConnector::new(config).unwrap();
+        let receiver = match jetstream.connect().await {
+            Ok(receiver) => {
+                backoff = std::time::Duration::from_secs(1);
+                receiver
+            }
+            Err(e) => {
+                println!("Failed to connect to Jetstream ({e}); retrying in {backoff:?}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                continue;
+            }
+        };
+
+        while let Ok(event) = receiver.recv_async().await {
+            if let Commit(CommitEvent::Create { info, commit, .. }) = event {
+                cursor = Some(info.time_us);
+                let mut handled_mention = false;
+                let event_did = info.did.to_string();
+                if let AppBskyFeedPost(record) = &commit.record {
+                    let matches = record.facets.as_ref().is_some_and(|facets| {
+                        facets.iter().any(|facet| {
+                            facet.data.features.iter().any(|feature| {
+                                matches!(feature, Union::Refs(Mention(m)) if m.data.did == target_did)
+                            })
                         })
-                    })
-                });
-                if matches {
-                    if !dry_run {
-                        handle_message(&agent, &event_did, &commit).await;
-                    } else {
-                        println!("Dry run mode: Would handle message from {}", event_did);
-                        println!("Message to post: {:#?}", record);
+                    });
+                    if matches {
+                        let uri = post_uri(
+                            &event_did,
+                            &commit.info.collection.to_string(),
+                            &commit.info.rkey.to_string(),
+                        );
+                        if seen.check_and_insert(&uri) {
+                            println!("Skipping already-handled post {uri}");
+                        } else if !dry_run {
+                            handle_message(&agent, backend, &event_did, &commit).await;
+                            handled_mention = true;
+                        } else {
+                            println!("Dry run mode: Would handle message from {}", event_did);
+                            println!("Message to post: {:#?}", record);
+                            handled_mention = true;
+                        }
+                    }
+                }
+                if handled_mention || last_cursor_save.elapsed() >= CURSOR_SAVE_INTERVAL {
+                    if let Err(e) = stream_state::save_cursor(info.time_us) {
+                        println!("Failed to persist Jetstream cursor: {e}");
+                    }
+                    if let Err(e) = stream_state::save_seen(&seen) {
+                        println!("Failed to persist seen-URI state: {e}");
                     }
+                    last_cursor_save = std::time::Instant::now();
                 }
             }
         }
+        println!("Jetstream connection dropped; reconnecting...");
     }
-    println!("Exiting streaming mode!");
-    Ok(())
 }
 
 pub fn extract_number(text: &str) -> Option<String> {
@@ -190,7 +284,7 @@ pub fn extract_number(text: &str) -> Option<String> {
         })
 }
 
-pub async fn do_pisearch(text: &str) -> anyhow::Result<String> {
+pub async fn do_pisearch(backend: &dyn PiBackend, text: &str) -> anyhow::Result<String> {
     let number = match extract_number(text) {
         Some(num) => num,
         None => {
@@ -198,37 +292,24 @@ pub async fn do_pisearch(text: &str) -> anyhow::Result<String> {
         }
     };
     println!("Searching for: {}", number);
-
-    let search_result = match search_pi_str(&number).await {
-        Ok(result) => result,
+    let digits = pi_index::describe_digit_count(backend.digit_count());
+
+    match backend.search(&number).await {
+        Ok(None) => Ok(format!("Sorry, I couldn't find {number} in the first {digits} digits of Pi. It's me, not you; every number should be in Pi if I had more.")),
+        Ok(Some(pi_match)) => Ok(format!(
+            "I found {} at position {}. It appears {} times in the first {digits} digits of pi. Thanks for searching!\n\nFind all the #pi you can eat at https://angio.net/pi/",
+            number, pi_match.position, pi_match.count
+        )),
         Err(e) => {
             println!("Error searching: {e}");
-            return Err(anyhow!("Error searching: {e}"));
-        }
-    };
-    if search_result.status == "error" {
-        return Err(anyhow!("Error searching pi"));
-    }
-    match search_result.r.first() {
-        None =>
-            Ok(format!("Sorry, I couldn't find {number} in the first 200m digits of Pi. It's me, not you; every number should be in Pi if I had more.")),
-        Some(entry) => {
-            if entry.status == "notfound" {
-                Ok(format!("Sorry, I couldn't find {number} in the first 200m digits of Pi. It's me, not you; every number should be in Pi if I had more."))
-            } else {
-            Ok(format!(
-        "I found {} at position {}. It appears {} times in the first 200 million digits of pi. Thanks for searching!\n\nFind all the #pi you can eat at https://angio.net/pi/",
-        number,
-        search_result.r.first().map_or(0, |entry| entry.p),
-        search_result.r.len()
-    ))
+            Err(anyhow!("Error searching: {e}"))
         }
     }
 }
-}
 
 pub async fn handle_message(
     agent: &BskyAgent,
+    backend: &dyn PiBackend,
     did: &str,
     commit: &jetstream_oxide::events::commit::CommitData,
 ) {
@@ -238,14 +319,9 @@ pub async fn handle_message(
         // Second ugh - we need to craft a URI for the post since it doesn't
         // seem to be included in the data.
         // at://<did>/app.bsky.feed.post/<rkey>
-        let uri = format!(
-            "at://{}/{}/{}",
-            did,
-            commit.info.collection.to_string(),
-            commit.info.rkey
-        );
+        let uri = post_uri(did, &commit.info.collection.to_string(), &commit.info.rkey.to_string());
 
-        let reply_text = match do_pisearch(&record.text).await {
+        let reply_text = match commands::dispatch(backend, &record.text).await {
             Ok(text) => text,
             Err(e) => {
                 println!("Error searching pi: {}", e);
@@ -287,7 +363,14 @@ pub async fn handle_message(
             text: reply_text,
         };
 
-        if let Err(e) = agent.create_record(record_data).await {
+        let reply = session::with_refresh(agent, || async {
+            agent
+                .create_record(record_data.clone())
+                .await
+                .map_err(|e| anyhow::anyhow!("{e}"))
+        })
+        .await;
+        if let Err(e) = reply {
             println!("Error creating record: {}", e);
         }
     }
@@ -297,34 +380,55 @@ pub async fn handle_message(
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
+    if let Commands::BuildIndex { pi_file, index } = &cli.command {
+        pi_index::build_index(pi_file, index)?;
+        println!("Wrote suffix array index to {}", index.display());
+        return Ok(());
+    }
+
     let credentials = read_credentials()?;
+    let backend = build_backend(&cli)?;
 
-    if cli.command == Commands::Stream {
-        streaming_mode(&credentials, cli.dry_run).await?;
-        return Ok(());
+    match &cli.command {
+        Commands::Stream => {
+            streaming_mode(&credentials, backend.as_ref(), cli.dry_run).await?;
+            return Ok(());
+        }
+        Commands::List => {
+            let agent = session::login(&credentials.username, &credentials.password).await?;
+            records::list(&agent).await?;
+            return Ok(());
+        }
+        Commands::Delete { rkey } => {
+            let agent = session::login(&credentials.username, &credentials.password).await?;
+            records::delete(&agent, rkey, cli.dry_run).await?;
+            return Ok(());
+        }
+        Commands::Random | Commands::Today => {}
+        Commands::BuildIndex { .. } => unreachable!("handled above"),
     }
     let number = match cli.command {
         Commands::Today => get_today_date().parse::<u32>()?,
         _ => generate_random_number(),
     };
 
-    let search_result = search_pi(number).await?;
+    let search_result = backend.search(&number.to_string()).await?;
+    let digits = pi_index::describe_digit_count(backend.digit_count());
 
-    let agent = BskyAgent::builder().build().await?;
-    let _session = agent.login(&credentials.username, &credentials.password).await?;
+    let agent = session::login(&credentials.username, &credentials.password).await?;
 
     let post_content = match cli.command {
         Commands::Today => format!(
-            "I found today in pi, {}, at position {}. It appears {} times in the first 200 million digits of pi.\n\nFind all the #pi you can eat at https://angio.net/pi/",
+            "I found today in pi, {}, at position {}. It appears {} times in the first {digits} digits of pi.\n\nFind all the #pi you can eat at https://angio.net/pi/",
             number,
-            search_result.r.first().map_or(0, |entry| entry.p),
-            search_result.r.len()
+            search_result.map_or(0, |m| m.position),
+            search_result.map_or(0, |m| m.count)
         ),
         _ => format!(
-            "The string {} was found at position {} in Pi. It appears {} times in the first 200 million digits of pi.\n\nFind all the #pi you can eat at https://angio.net/pi/",
+            "The string {} was found at position {} in Pi. It appears {} times in the first {digits} digits of pi.\n\nFind all the #pi you can eat at https://angio.net/pi/",
             number,
-            search_result.r.first().map_or(0, |entry| entry.p),
-            search_result.r.len()
+            search_result.map_or(0, |m| m.position),
+            search_result.map_or(0, |m| m.count)
         ),
     };
 
@@ -0,0 +1,553 @@
+//! Local, offline replacement for the angio.net Pi search API.
+//!
+//! Digits of pi are stored as a flat ASCII file (one byte per digit, no
+//! decimal point) alongside a suffix array: a file of little-endian `i64`
+//! suffix start offsets, sorted lexicographically by the suffix they point
+//! at. Both files are `mmap`'d so we never load the whole 200M+ digit corpus
+//! into the heap. A query is two binary searches over the suffix array
+//! (first occurrence, first non-occurrence) followed by a linear scan of the
+//! matched slots to find the earliest offset, since the array is ordered
+//! lexically rather than positionally.
+
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+/// Result of a successful lookup against the indexed digits of pi.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PiMatch {
+    /// 1-based offset of the earliest occurrence, matching the wording the
+    /// bot has always used ("I found X at position P").
+    pub position: u64,
+    pub count: u64,
+}
+
+/// Something that can answer "where does this numeric string first occur in
+/// pi, and how many times" -- whether backed by angio.net or a local index.
+#[async_trait::async_trait]
+pub trait PiBackend: Send + Sync {
+    async fn search(&self, number: &str) -> anyhow::Result<Option<PiMatch>>;
+
+    /// Reverse lookup: the `len` digits of pi starting at the 1-based
+    /// `position`, or `None` if `position` is past the end of the indexed
+    /// digits. Backends that can't do this (e.g. the remote angio.net API)
+    /// should return an error.
+    async fn digits_at(&self, position: u64, len: usize) -> anyhow::Result<Option<String>>;
+
+    /// How many digits of pi this backend can answer queries against, for
+    /// wording replies accurately instead of hardcoding a fixed figure.
+    fn digit_count(&self) -> u64;
+}
+
+/// Formats a digit count for use in a reply, e.g. "200 million" for the
+/// angio.net-sized corpus or the raw count for an arbitrary local file.
+pub fn describe_digit_count(count: u64) -> String {
+    if count >= 1_000_000 && count % 1_000_000 == 0 {
+        format!("{} million", count / 1_000_000)
+    } else {
+        count.to_string()
+    }
+}
+
+/// Digits of pi plus a suffix array, both mmap'd from disk.
+pub struct LocalPiIndex {
+    digits: Mmap,
+    suffix_array: Mmap,
+}
+
+impl LocalPiIndex {
+    pub fn open(digits_path: &Path, index_path: &Path) -> io::Result<Self> {
+        let digits_file = File::open(digits_path)?;
+        let index_file = File::open(index_path)?;
+        // Safety: both files are opened read-only for the lifetime of the
+        // index and are not expected to be mutated out from under us.
+        let digits = unsafe { Mmap::map(&digits_file)? };
+        let suffix_array = unsafe { Mmap::map(&index_file)? };
+        Ok(Self {
+            digits,
+            suffix_array,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.suffix_array.len() / 8
+    }
+
+    fn sa(&self, i: usize) -> i64 {
+        let bytes = &self.suffix_array[i * 8..i * 8 + 8];
+        i64::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    /// Compares the suffix at `offset`, truncated to `pattern.len()` bytes,
+    /// against `pattern`. A suffix shorter than `pattern` can never contain
+    /// it as a prefix, so it sorts as `Less` rather than `Equal`.
+    fn cmp_suffix(&self, offset: i64, pattern: &[u8]) -> Ordering {
+        let start = offset as usize;
+        let end = (start + pattern.len()).min(self.digits.len());
+        let probe = &self.digits[start..end];
+        match probe.cmp(pattern) {
+            Ordering::Equal if probe.len() < pattern.len() => Ordering::Less,
+            other => other,
+        }
+    }
+
+    fn lower_bound(&self, pattern: &[u8]) -> usize {
+        let (mut lo, mut hi) = (0usize, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.cmp_suffix(self.sa(mid), pattern) == Ordering::Less {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    fn upper_bound(&self, pattern: &[u8]) -> usize {
+        let (mut lo, mut hi) = (0usize, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.cmp_suffix(self.sa(mid), pattern) == Ordering::Greater {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+
+    pub fn search(&self, number: &str) -> Option<PiMatch> {
+        if number.is_empty() {
+            return None;
+        }
+        let pattern = number.as_bytes();
+        let lower = self.lower_bound(pattern);
+        let upper = self.upper_bound(pattern);
+        if lower == upper {
+            return None;
+        }
+        let earliest = (lower..upper).map(|i| self.sa(i)).min()?;
+        Some(PiMatch {
+            position: earliest as u64 + 1,
+            count: (upper - lower) as u64,
+        })
+    }
+
+    pub fn digits_at(&self, position: u64, len: usize) -> Option<String> {
+        if position == 0 {
+            return None;
+        }
+        let start = (position - 1) as usize;
+        if start >= self.digits.len() {
+            return None;
+        }
+        let end = (start + len).min(self.digits.len());
+        Some(String::from_utf8_lossy(&self.digits[start..end]).into_owned())
+    }
+}
+
+#[async_trait::async_trait]
+impl PiBackend for LocalPiIndex {
+    async fn search(&self, number: &str) -> anyhow::Result<Option<PiMatch>> {
+        Ok(LocalPiIndex::search(self, number))
+    }
+
+    async fn digits_at(&self, position: u64, len: usize) -> anyhow::Result<Option<String>> {
+        Ok(LocalPiIndex::digits_at(self, position, len))
+    }
+
+    fn digit_count(&self) -> u64 {
+        self.digits.len() as u64
+    }
+}
+
+/// Builds a suffix array for `digits` and writes it to `index_path` as
+/// little-endian `i64` offsets.
+///
+/// This is a one-time, offline step (run it once after downloading a new
+/// digits file, not on every startup). Construction uses SA-IS (see
+/// [`sa_is`]), which is linear in time and space, so it scales to the full
+/// ~200M-digit file without the memory or runtime blowup a comparison sort
+/// would hit.
+pub fn build_index(digits_path: &Path, index_path: &Path) -> io::Result<()> {
+    let digits = std::fs::read(digits_path)?;
+    let suffixes = build_suffix_array(&digits);
+
+    let mut out = Vec::with_capacity(suffixes.len() * 8);
+    for offset in suffixes {
+        out.extend_from_slice(&offset.to_le_bytes());
+    }
+    std::fs::write(index_path, out)
+}
+
+/// Every byte value 0-255 plus the sentinel appended in [`build_suffix_array`]
+/// fits in this many symbols.
+const BASE_ALPHABET_SIZE: usize = 257;
+
+/// Builds the sorted list of suffix start offsets for `digits` via SA-IS.
+fn build_suffix_array(digits: &[u8]) -> Vec<i64> {
+    let n = digits.len();
+    // SA-IS needs a unique, strictly-minimal sentinel appended so every
+    // suffix (and the recursive reductions below) has an unambiguous end;
+    // shift digit bytes up by one so 0 is free for it.
+    let mut s: Vec<i64> = Vec::with_capacity(n + 1);
+    s.extend(digits.iter().map(|&b| b as i64 + 1));
+    s.push(0);
+    let sa = sa_is(&s, BASE_ALPHABET_SIZE);
+    // Drop the sentinel's own suffix (always sorts first) to get back the
+    // suffix array of `digits` alone.
+    sa.into_iter().filter(|&p| (p as usize) < n).collect()
+}
+
+fn classify_types(s: &[i64]) -> Vec<bool> {
+    let n = s.len();
+    let mut is_s = vec![false; n];
+    is_s[n - 1] = true;
+    for i in (0..n - 1).rev() {
+        is_s[i] = if s[i] < s[i + 1] {
+            true
+        } else if s[i] > s[i + 1] {
+            false
+        } else {
+            is_s[i + 1]
+        };
+    }
+    is_s
+}
+
+/// An "LMS" (leftmost S-type) position: S-type with an L-type predecessor.
+/// These split the string into the substrings SA-IS sorts and names to
+/// build its recursive reduction.
+fn is_lms(is_s: &[bool], i: usize) -> bool {
+    i > 0 && is_s[i] && !is_s[i - 1]
+}
+
+fn bucket_sizes(s: &[i64], alphabet_size: usize) -> Vec<usize> {
+    let mut sizes = vec![0usize; alphabet_size];
+    for &c in s {
+        sizes[c as usize] += 1;
+    }
+    sizes
+}
+
+fn bucket_heads(sizes: &[usize]) -> Vec<usize> {
+    let mut heads = vec![0usize; sizes.len()];
+    let mut sum = 0;
+    for i in 0..sizes.len() {
+        heads[i] = sum;
+        sum += sizes[i];
+    }
+    heads
+}
+
+fn bucket_tails(sizes: &[usize]) -> Vec<usize> {
+    let mut tails = vec![0usize; sizes.len()];
+    let mut sum = 0;
+    for i in 0..sizes.len() {
+        sum += sizes[i];
+        tails[i] = sum;
+    }
+    tails
+}
+
+/// Seeds `sa` with `positions` at the tail of each position's character
+/// bucket, decrementing as it goes. Passing `positions` in descending
+/// sorted order yields ascending sorted order within each bucket; an
+/// arbitrary order still places each position in the right bucket, which is
+/// all the first induction pass (used only to discover LMS-substring order)
+/// needs.
+fn seed_lms(s: &[i64], positions: &[usize], tails: &mut [usize], sa: &mut [i64]) {
+    for &p in positions {
+        let c = s[p] as usize;
+        tails[c] -= 1;
+        sa[tails[c]] = p as i64;
+    }
+}
+
+/// Fills in L-type suffixes left-to-right: whenever `sa[i]`'s predecessor is
+/// L-type, it must sort immediately after the current head of its bucket.
+fn induce_l(s: &[i64], is_s: &[bool], sizes: &[usize], sa: &mut [i64]) {
+    let mut heads = bucket_heads(sizes);
+    for i in 0..sa.len() {
+        let p = sa[i];
+        if p <= 0 {
+            continue;
+        }
+        let j = (p - 1) as usize;
+        if !is_s[j] {
+            let c = s[j] as usize;
+            sa[heads[c]] = j as i64;
+            heads[c] += 1;
+        }
+    }
+}
+
+/// Mirror of [`induce_l`] for S-type suffixes, filled in right-to-left from
+/// the tail of each bucket.
+fn induce_s(s: &[i64], is_s: &[bool], sizes: &[usize], sa: &mut [i64]) {
+    let mut tails = bucket_tails(sizes);
+    for i in (0..sa.len()).rev() {
+        let p = sa[i];
+        if p <= 0 {
+            continue;
+        }
+        let j = (p - 1) as usize;
+        if is_s[j] {
+            let c = s[j] as usize;
+            tails[c] -= 1;
+            sa[tails[c]] = j as i64;
+        }
+    }
+}
+
+/// Compares the LMS substrings starting at `i` and `j` (each running up to
+/// and including the next LMS position) for equality.
+fn lms_substrings_equal(s: &[i64], is_s: &[bool], i: usize, j: usize) -> bool {
+    let n = s.len();
+    let mut offset = 0usize;
+    loop {
+        let (pi, pj) = (i + offset, j + offset);
+        if pi >= n || pj >= n {
+            return pi >= n && pj >= n;
+        }
+        if s[pi] != s[pj] {
+            return false;
+        }
+        let pi_is_lms = offset > 0 && is_lms(is_s, pi);
+        let pj_is_lms = offset > 0 && is_lms(is_s, pj);
+        if pi_is_lms || pj_is_lms {
+            return pi_is_lms && pj_is_lms;
+        }
+        offset += 1;
+    }
+}
+
+/// Builds a suffix array for `s`, an array of symbols in `0..alphabet_size`
+/// with a unique minimal value at `s[s.len() - 1]`, via the SA-IS algorithm
+/// (Nong, Zhang, Chen 2009): classify suffixes as S-type/L-type, induce-sort
+/// around a first unsorted seeding of the LMS suffixes to discover
+/// LMS-substring order, name the substrings to build a reduced problem at
+/// most half the size, recurse on it (or read the answer off directly once
+/// every name is already unique), then induce-sort once more from the now
+/// fully-sorted LMS suffixes. Linear in both time and space, unlike prefix
+/// doubling's O(n log^2 n) comparisons and extra O(n) buffers per round, or
+/// a plain comparison sort's O(n log n) comparisons of O(n)-length suffixes.
+fn sa_is(s: &[i64], alphabet_size: usize) -> Vec<i64> {
+    let n = s.len();
+    if n == 1 {
+        return vec![0];
+    }
+
+    let is_s = classify_types(s);
+    let lms_positions: Vec<usize> = (1..n).filter(|&i| is_lms(&is_s, i)).collect();
+    let sizes = bucket_sizes(s, alphabet_size);
+
+    let mut sa = vec![-1i64; n];
+    let mut tails = bucket_tails(&sizes);
+    let seed_order: Vec<usize> = lms_positions.iter().rev().copied().collect();
+    seed_lms(s, &seed_order, &mut tails, &mut sa);
+    induce_l(s, &is_s, &sizes, &mut sa);
+    induce_s(s, &is_s, &sizes, &mut sa);
+
+    let sorted_lms: Vec<usize> = sa
+        .iter()
+        .map(|&p| p as usize)
+        .filter(|&p| is_lms(&is_s, p))
+        .collect();
+
+    let mut names = vec![-1i64; n];
+    let mut name = 0i64;
+    names[sorted_lms[0]] = 0;
+    for w in 1..sorted_lms.len() {
+        let (prev, cur) = (sorted_lms[w - 1], sorted_lms[w]);
+        if !lms_substrings_equal(s, &is_s, prev, cur) {
+            name += 1;
+        }
+        names[cur] = name;
+    }
+    let reduced_alphabet_size = (name + 1) as usize;
+
+    let s1: Vec<i64> = lms_positions.iter().map(|&p| names[p]).collect();
+
+    // If every LMS substring already got a distinct name, s1 is a
+    // permutation of 0..s1.len() and its suffix array can be read off
+    // directly: a unique first symbol fully determines suffix order.
+    let sa1: Vec<i64> = if reduced_alphabet_size == s1.len() {
+        let mut sa1 = vec![0i64; s1.len()];
+        for (i, &nm) in s1.iter().enumerate() {
+            sa1[nm as usize] = i as i64;
+        }
+        sa1
+    } else {
+        sa_is(&s1, reduced_alphabet_size)
+    };
+
+    let sorted_lms_positions: Vec<usize> =
+        sa1.iter().map(|&i| lms_positions[i as usize]).collect();
+
+    let mut sa = vec![-1i64; n];
+    let mut tails = bucket_tails(&sizes);
+    let seed_order: Vec<usize> = sorted_lms_positions.iter().rev().copied().collect();
+    seed_lms(s, &seed_order, &mut tails, &mut sa);
+    induce_l(s, &is_s, &sizes, &mut sa);
+    induce_s(s, &is_s, &sizes, &mut sa);
+
+    sa
+}
+
+/// The existing angio.net-backed search, wrapped behind [`PiBackend`] so
+/// callers don't need to care whether digits are local or remote.
+pub struct AngioBackend;
+
+#[async_trait::async_trait]
+impl PiBackend for AngioBackend {
+    async fn search(&self, number: &str) -> anyhow::Result<Option<PiMatch>> {
+        let result = crate::search_pi_str(number)
+            .await
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        if result.status == "error" {
+            return Err(anyhow::anyhow!("Error searching pi"));
+        }
+        match result.r.first() {
+            None => Ok(None),
+            Some(entry) if entry.status == "notfound" => Ok(None),
+            Some(entry) => Ok(Some(PiMatch {
+                position: entry.p,
+                count: result.r.len() as u64,
+            })),
+        }
+    }
+
+    async fn digits_at(&self, _position: u64, _len: usize) -> anyhow::Result<Option<String>> {
+        Err(anyhow::anyhow!(
+            "position lookup needs a local --pi-file/--index; angio.net doesn't support it"
+        ))
+    }
+
+    fn digit_count(&self) -> u64 {
+        200_000_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_index(dir: &Path, digits: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+        let digits_path = dir.join("digits.txt");
+        let index_path = dir.join("digits.idx");
+        std::fs::File::create(&digits_path)
+            .unwrap()
+            .write_all(digits.as_bytes())
+            .unwrap();
+        build_index(&digits_path, &index_path).unwrap();
+        (digits_path, index_path)
+    }
+
+    #[test]
+    fn finds_first_occurrence_and_count() {
+        let dir = tempfile::tempdir().unwrap();
+        // "14159" occurs at offsets 0, 9 and 15.
+        let (digits_path, index_path) = write_index(dir.path(), "14159265314159314159");
+        let index = LocalPiIndex::open(&digits_path, &index_path).unwrap();
+
+        let result = index.search("14159").unwrap();
+        assert_eq!(result.position, 1);
+        assert_eq!(result.count, 3);
+    }
+
+    #[test]
+    fn missing_pattern_returns_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let (digits_path, index_path) = write_index(dir.path(), "14159265358979");
+        let index = LocalPiIndex::open(&digits_path, &index_path).unwrap();
+
+        assert!(index.search("99999").is_none());
+    }
+
+    #[test]
+    fn pattern_longer_than_any_suffix_is_no_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let (digits_path, index_path) = write_index(dir.path(), "314159");
+        let index = LocalPiIndex::open(&digits_path, &index_path).unwrap();
+
+        assert!(index.search("4159000").is_none());
+    }
+
+    #[test]
+    fn empty_pattern_returns_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let (digits_path, index_path) = write_index(dir.path(), "314159");
+        let index = LocalPiIndex::open(&digits_path, &index_path).unwrap();
+
+        assert!(index.search("").is_none());
+    }
+
+    #[test]
+    fn digits_at_reads_forward_from_position() {
+        let dir = tempfile::tempdir().unwrap();
+        let (digits_path, index_path) = write_index(dir.path(), "3141592653589793");
+        let index = LocalPiIndex::open(&digits_path, &index_path).unwrap();
+
+        assert_eq!(index.digits_at(1, 5), Some("31415".to_string()));
+        assert_eq!(index.digits_at(5, 4), Some("5926".to_string()));
+        assert_eq!(index.digits_at(0, 5), None);
+        assert_eq!(index.digits_at(100, 5), None);
+    }
+
+    #[test]
+    fn digit_count_reflects_the_loaded_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (digits_path, index_path) = write_index(dir.path(), "3141592653589793");
+        let index = LocalPiIndex::open(&digits_path, &index_path).unwrap();
+
+        assert_eq!(index.digit_count(), 16);
+    }
+
+    #[test]
+    fn describe_digit_count_formats_large_and_small_counts() {
+        assert_eq!(describe_digit_count(200_000_000), "200 million");
+        assert_eq!(describe_digit_count(16), "16");
+    }
+
+    fn naive_suffix_array(digits: &[u8]) -> Vec<i64> {
+        let mut suffixes: Vec<i64> = (0..digits.len() as i64).collect();
+        suffixes.sort_by(|&a, &b| digits[a as usize..].cmp(&digits[b as usize..]));
+        suffixes
+    }
+
+    #[test]
+    fn sa_is_matches_a_naive_full_suffix_sort() {
+        let cases: &[&[u8]] = &[
+            b"",
+            b"1",
+            b"11",
+            b"12",
+            b"21",
+            b"212",
+            b"121",
+            b"1221",
+            b"123321",
+            b"3141592653589793238462643383279502884197",
+            b"11111111",
+            b"0123456789",
+            b"9876543210",
+            b"121212121212",
+            b"100000000001",
+            b"314159314159314159",
+        ];
+        for digits in cases {
+            assert_eq!(
+                build_suffix_array(digits),
+                naive_suffix_array(digits),
+                "mismatch for {:?}",
+                String::from_utf8_lossy(digits)
+            );
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Repo management: list and delete the bot's own `app.bsky.feed.post`
+//! records, for cleaning up after a bad post or auditing what the bot has
+//! written. Reuses the same logged-in `BskyAgent` the posting commands use.
+
+use atrium_api::record::KnownRecord::AppBskyFeedPost;
+use atrium_api::types::string::{Nsid, RecordKey};
+use bsky_sdk::BskyAgent;
+
+use crate::session;
+
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+async fn own_did(agent: &BskyAgent) -> Result<atrium_api::types::string::Did, Box<dyn std::error::Error>> {
+    Ok(agent
+        .get_session()
+        .await
+        .ok_or("not logged in")?
+        .did
+        .clone())
+}
+
+/// Prints the rkey and text of each of the bot's posts, paging through the
+/// full listing rather than just the first page.
+pub async fn list(agent: &BskyAgent) -> Result<(), Box<dyn std::error::Error>> {
+    let repo = own_did(agent).await?;
+    let mut cursor = None;
+
+    loop {
+        let params = atrium_api::com::atproto::repo::list_records::ParametersData {
+            repo: repo.clone().into(),
+            collection: Nsid::new(POST_COLLECTION.to_string())?,
+            cursor: cursor.clone(),
+            limit: None,
+            reverse: None,
+        };
+        let output = session::with_refresh(agent, || async {
+            agent
+                .api
+                .com
+                .atproto
+                .repo
+                .list_records(params.clone().into())
+                .await
+                .map_err(|e| anyhow::anyhow!("{e}"))
+        })
+        .await?;
+
+        for record in &output.records {
+            let rkey = record.uri.rsplit('/').next().unwrap_or_default();
+            match &record.value {
+                AppBskyFeedPost(post) => println!("{rkey}\t{}", post.text),
+                _ => println!("{rkey}\t<non-post record>"),
+            }
+        }
+
+        cursor = output.cursor.clone();
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes a single post by its record key. Honors `dry_run` by printing
+/// what would be deleted instead of calling `deleteRecord`.
+pub async fn delete(
+    agent: &BskyAgent,
+    rkey: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dry_run {
+        println!("Dry run mode: would delete post {rkey}");
+        return Ok(());
+    }
+
+    let repo = own_did(agent).await?;
+    let input = atrium_api::com::atproto::repo::delete_record::InputData {
+        collection: Nsid::new(POST_COLLECTION.to_string())?,
+        repo: repo.into(),
+        rkey: RecordKey::new(rkey.to_string())?,
+        swap_commit: None,
+        swap_record: None,
+    };
+    session::with_refresh(agent, || async {
+        agent
+            .api
+            .com
+            .atproto
+            .repo
+            .delete_record(input.clone().into())
+            .await
+            .map_err(|e| anyhow::anyhow!("{e}"))
+    })
+    .await?;
+    println!("Deleted post {rkey}");
+    Ok(())
+}
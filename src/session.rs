@@ -0,0 +1,75 @@
+//! Persists the atrium session (access + refresh JWT, DID, PDS endpoint) to
+//! disk so we don't have to log in with username/password on every run, and
+//! refreshes it transparently when a call comes back with an expired-auth
+//! error. This keeps a long-running `stream` session alive for days and
+//! avoids burning through rate limits on every `random`/`today` cron run.
+
+use std::future::Future;
+use std::path::PathBuf;
+
+use bsky_sdk::BskyAgent;
+
+fn session_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let home_dir = std::env::var("HOME")?;
+    Ok(PathBuf::from(format!("{}/.pibot_session.json", home_dir)))
+}
+
+/// Builds a logged-in agent, resuming the last saved session if one is on
+/// disk and still valid, otherwise logging in fresh with `username`/
+/// `password` and saving the resulting session for next time.
+pub async fn login(
+    username: &str,
+    password: &str,
+) -> Result<BskyAgent, Box<dyn std::error::Error>> {
+    let agent = BskyAgent::builder().build().await?;
+    let path = session_path()?;
+
+    if let Ok(data) = std::fs::read_to_string(&path) {
+        if let Ok(session) = serde_json::from_str(&data) {
+            if agent.resume_session(session).await.is_ok() {
+                println!("Resumed session from {}", path.display());
+                return Ok(agent);
+            }
+            println!("Stored session at {} is no longer valid", path.display());
+        }
+    }
+
+    agent.login(username, password).await?;
+    save(&agent).await?;
+    Ok(agent)
+}
+
+/// Writes the agent's current session to `~/.pibot_session.json`.
+pub async fn save(agent: &BskyAgent) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(session) = agent.get_session().await {
+        let path = session_path()?;
+        std::fs::write(path, serde_json::to_string(&session)?)?;
+    }
+    Ok(())
+}
+
+/// Runs `op` and, if it fails with an expired-auth error, refreshes the
+/// session and retries once before giving up.
+pub async fn with_refresh<T, Fut>(
+    agent: &BskyAgent,
+    op: impl Fn() -> Fut,
+) -> anyhow::Result<T>
+where
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    match op().await {
+        Ok(value) => Ok(value),
+        Err(e) if is_expired_auth(&e) => {
+            println!("Access token expired, refreshing session");
+            agent.refresh_session().await?;
+            save(agent).await.map_err(|e| anyhow::anyhow!("{e}"))?;
+            op().await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn is_expired_auth(e: &anyhow::Error) -> bool {
+    let msg = e.to_string().to_lowercase();
+    msg.contains("expired") || msg.contains("invalidtoken") || msg.contains("authenticationrequired")
+}